@@ -31,10 +31,93 @@ pub trait Source<G: CurveAffine> {
     /// Parses the element from the source. Fails if the point is at infinity.
     fn add_assign_mixed(&mut self, to: &mut <G as CurveAffine>::Projective) -> Result<(), SynthesisError>;
 
+    /// Parses the element from the source directly into a lazily-initialized
+    /// bucket. Implementors that can hand out the affine point itself (rather
+    /// than only accumulating into an existing projective point) should
+    /// override this to avoid paying for a mixed addition into the identity
+    /// element the first time a bucket is touched.
+    fn add_assign_to_bucket(&mut self, to: &mut Bucket<G>) -> Result<(), SynthesisError> {
+        let mut acc = G::Projective::zero();
+        self.add_assign_mixed(&mut acc)?;
+        to.add_assign(&acc.into_affine());
+        Ok(())
+    }
+
+    /// Returns the next base as an owned affine point without adding it
+    /// anywhere. Used by the batched-affine accumulation path (see
+    /// `batch_add_assign`), which needs to hold on to bases across passes
+    /// rather than applying them inline.
+    #[cfg(feature = "multiexp-batch-affine")]
+    fn get(&mut self) -> Result<G, SynthesisError> {
+        let mut acc = G::Projective::zero();
+        self.add_assign_mixed(&mut acc)?;
+        Ok(acc.into_affine())
+    }
+
     /// Skips `amt` elements from the source, avoiding deserialization.
     fn skip(&mut self, amt: usize) -> Result<(), SynthesisError>;
 }
 
+/// The state of a single bucket in the bucket method, kept as a bare affine
+/// point until a second base lands in it to avoid a needless addition with
+/// the identity element.
+pub enum Bucket<G: CurveAffine> {
+    None,
+    Affine(G),
+    Projective(G::Projective),
+}
+
+impl<G: CurveAffine> Bucket<G> {
+    fn add_assign(&mut self, other: &G) {
+        *self = match self {
+            Bucket::None => Bucket::Affine(*other),
+            Bucket::Affine(affine) => {
+                let mut p = affine.into_projective();
+                p.add_assign_mixed(other);
+                Bucket::Projective(p)
+            },
+            Bucket::Projective(p) => {
+                p.add_assign_mixed(other);
+                return;
+            }
+        };
+    }
+
+    /// Adds the accumulated value of this bucket into `acc`, treating `None`
+    /// as the identity element.
+    fn add_to(self, acc: &mut G::Projective) {
+        match self {
+            Bucket::None => {},
+            Bucket::Affine(affine) => acc.add_assign_mixed(&affine),
+            Bucket::Projective(p) => acc.add_assign(&p),
+        }
+    }
+
+    /// Combines two independently-accumulated buckets for the same index,
+    /// e.g. when reducing the per-chunk bucket arrays produced by
+    /// intra-window parallelism.
+    #[cfg(feature = "multiexp-intra-window-parallelism")]
+    fn merge(self, other: Bucket<G>) -> Bucket<G> {
+        match (self, other) {
+            (Bucket::None, other) => other,
+            (this, Bucket::None) => this,
+            (Bucket::Affine(a), Bucket::Affine(b)) => {
+                let mut p = a.into_projective();
+                p.add_assign_mixed(&b);
+                Bucket::Projective(p)
+            },
+            (Bucket::Affine(a), Bucket::Projective(mut p)) | (Bucket::Projective(mut p), Bucket::Affine(a)) => {
+                p.add_assign_mixed(&a);
+                Bucket::Projective(p)
+            },
+            (Bucket::Projective(mut a), Bucket::Projective(b)) => {
+                a.add_assign(&b);
+                Bucket::Projective(a)
+            },
+        }
+    }
+}
+
 impl<G: CurveAffine> SourceBuilder<G> for (Arc<Vec<G>>, usize) {
     type Source = (Arc<Vec<G>>, usize);
 
@@ -60,6 +143,38 @@ impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
         Ok(())
     }
 
+    fn add_assign_to_bucket(&mut self, to: &mut Bucket<G>) -> Result<(), SynthesisError> {
+        if self.0.len() <= self.1 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases when adding from source").into());
+        }
+
+        if self.0[self.1].is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity)
+        }
+
+        to.add_assign(&self.0[self.1]);
+
+        self.1 += 1;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "multiexp-batch-affine")]
+    fn get(&mut self) -> Result<G, SynthesisError> {
+        if self.0.len() <= self.1 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases when adding from source").into());
+        }
+
+        if self.0[self.1].is_zero() {
+            return Err(SynthesisError::UnexpectedIdentity)
+        }
+
+        let base = self.0[self.1];
+        self.1 += 1;
+
+        Ok(base)
+    }
+
     fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
         if self.0.len() <= self.1 {
             return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases skipping from source").into());
@@ -142,6 +257,216 @@ impl DensityTracker {
     }
 }
 
+/// One pass of batched-affine bucket accumulation: applies the first pair
+/// targeting each bucket using Montgomery's trick to share one field
+/// inversion across every pair instead of paying for one per addition, and
+/// returns the rest (same-bucket collisions) to retry in a later pass.
+#[cfg(feature = "multiexp-batch-affine")]
+fn batch_add_assign<G: CurveAffine>(
+    buckets: &mut [Bucket<G>],
+    pairs: Vec<(usize, G)>,
+) -> Vec<(usize, G)> {
+    let mut deferred = Vec::new();
+    let mut touched = vec![false; buckets.len()];
+
+    // Pending generic-formula additions: (bucket index, x1, y1, x2, y2),
+    // together with their denominator `x2 - x1` in `denominators`.
+    let mut generic = Vec::new();
+    let mut denominators = Vec::new();
+
+    for (idx, base) in pairs {
+        if touched[idx] {
+            deferred.push((idx, base));
+            continue;
+        }
+
+        match buckets[idx] {
+            Bucket::Projective(ref mut p) => {
+                // Already promoted out of the affine-only path (e.g. by a
+                // doubling in an earlier pass); fall back to mixed addition.
+                touched[idx] = true;
+                p.add_assign_mixed(&base);
+            },
+            Bucket::None => {
+                touched[idx] = true;
+                buckets[idx] = Bucket::Affine(base);
+            },
+            Bucket::Affine(existing) => {
+                touched[idx] = true;
+
+                if existing.is_zero() {
+                    buckets[idx] = Bucket::Affine(base);
+                } else if base.is_zero() {
+                    // Adding the identity is a no-op.
+                } else {
+                    let (x1, y1) = existing.as_xy();
+                    let (x2, y2) = base.as_xy();
+
+                    if x1 == x2 {
+                        if y1 == y2 {
+                            let mut p = existing.into_projective();
+                            p.add_assign_mixed(&base);
+                            buckets[idx] = Bucket::Projective(p);
+                        } else {
+                            buckets[idx] = Bucket::None;
+                        }
+                    } else {
+                        let mut d = *x2;
+                        d.sub_assign(x1);
+
+                        denominators.push(d);
+                        generic.push((idx, *x1, *y1, *x2, *y2));
+                    }
+                }
+            }
+        }
+    }
+
+    if !denominators.is_empty() {
+        let mut prefix = Vec::with_capacity(denominators.len());
+        let mut acc = <G::Base as Field>::one();
+        for d in &denominators {
+            prefix.push(acc);
+            acc.mul_assign(d);
+        }
+
+        let mut inv = acc.inverse().expect("denominator is nonzero: x1 != x2 was just checked");
+
+        for (i, (idx, x1, y1, x2, y2)) in generic.into_iter().enumerate().rev() {
+            let mut d_inv = prefix[i];
+            d_inv.mul_assign(&inv);
+            inv.mul_assign(&denominators[i]);
+
+            let mut lambda = y2;
+            lambda.sub_assign(&y1);
+            lambda.mul_assign(&d_inv);
+
+            let mut x3 = lambda;
+            x3.square();
+            x3.sub_assign(&x1);
+            x3.sub_assign(&x2);
+
+            let mut y3 = x1;
+            y3.sub_assign(&x3);
+            y3.mul_assign(&lambda);
+            y3.sub_assign(&y1);
+
+            buckets[idx] = Bucket::Affine(G::from_xy_unchecked(x3, y3));
+        }
+    }
+
+    deferred
+}
+
+/// Extracts the `c`-bit window starting at bit `segment * c` out of `repr`,
+/// touching only the limb(s) it straddles instead of shifting the whole
+/// repr.
+fn get_at<R: PrimeFieldRepr>(segment: u32, c: u32, repr: &R) -> u64 {
+    let skip_bits = (segment * c) as usize;
+    let skip_limbs = skip_bits / 64;
+    let skip_bits = (skip_bits % 64) as u32;
+
+    let limbs = repr.as_ref();
+
+    if skip_limbs >= limbs.len() {
+        return 0;
+    }
+
+    let mut result = limbs[skip_limbs] >> skip_bits;
+    if skip_bits > 0 {
+        if let Some(next) = limbs.get(skip_limbs + 1) {
+            result |= next << (64 - skip_bits);
+        }
+    }
+
+    result & ((1u64 << c) - 1)
+}
+
+/// Issues a software prefetch hint for `p` on x86_64; on every other target
+/// (32-bit x86 included) it is a no-op. Gated behind the `prefetch` feature.
+#[cfg(feature = "prefetch")]
+#[inline(always)]
+fn prefetch<T>(p: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch(p as *const i8, _MM_HINT_T0);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = p;
+    }
+}
+
+/// Runs the bucket-filling step of a single window over just
+/// `exponents[start..start + len]`, reading bases from a fresh `Source`
+/// skipped forward to `start`. One thread's share of a chunked window.
+#[cfg(feature = "multiexp-intra-window-parallelism")]
+fn process_window_chunk<Q, D, G, S>(
+    bases: S,
+    start: usize,
+    len: usize,
+    density_map: &D,
+    exponents: &[<G::Scalar as PrimeField>::Repr],
+    skip: u32,
+    c: u32,
+    handle_trivial: bool,
+) -> Result<(G::Projective, Vec<Bucket<G>>), SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let mut acc = G::Projective::zero();
+    let mut bases = bases.new();
+
+    // `start` indexes into `exponents`, but the base stream only advances
+    // once per *dense* exponent, so the number of bases to skip is the
+    // number of `true` density bits before `start`, not `start` itself
+    // (they only coincide for `FullDensity`).
+    let dense_before_start = density_map.as_ref().iter().take(start).filter(|&present| present).count();
+    bases.skip(dense_before_start)?;
+
+    let mut buckets: Vec<Bucket<G>> = (0..(1 << c) - 1).map(|_| Bucket::None).collect();
+
+    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+    let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+
+    let window = &exponents[start..start + len];
+    let densities = density_map.as_ref().iter().skip(start).take(len);
+
+    for (_i, (&exp, density)) in window.iter().zip(densities).enumerate() {
+        #[cfg(feature = "prefetch")]
+        {
+            if let Some(next) = window.get(_i + 1) {
+                prefetch(next as *const _);
+            }
+        }
+
+        if density {
+            if exp == zero {
+                bases.skip(1)?;
+            } else if exp == one {
+                if handle_trivial {
+                    bases.add_assign_mixed(&mut acc)?;
+                } else {
+                    bases.skip(1)?;
+                }
+            } else {
+                let exp = get_at(skip / c, c, &exp);
+
+                if exp != 0 {
+                    bases.add_assign_to_bucket(&mut buckets[(exp - 1) as usize])?;
+                } else {
+                    bases.skip(1)?;
+                }
+            }
+        }
+    }
+
+    Ok((acc, buckets))
+}
+
 /// This genious piece of code works in the following way:
 /// - choose `c` - the bit length of the region that one thread works on
 /// - make `2^c - 1` buckets and initialize them with `G = infinity` (that's equivalent of zero)
@@ -192,26 +517,147 @@ fn multiexp_inner<Q, D, G, S>(
         let bases = bases.clone();
         let exponents = exponents.clone();
         let density_map = density_map.clone();
+        #[cfg(feature = "multiexp-intra-window-parallelism")]
+        let pool = pool.clone();
 
         // This looks like a Pippenger’s algorithm
         pool.compute(move || {
             // Accumulate the result
             let mut acc = G::Projective::zero();
 
-            // Build a source for the bases
+            // Build a source for the bases. Under intra-window parallelism
+            // each chunk builds its own source (skipped to its offset)
+            // instead, so `bases` is kept around as a `SourceBuilder` there.
+            #[cfg(not(feature = "multiexp-intra-window-parallelism"))]
             let mut bases = bases.new();
 
             // Create buckets to place remainders s mod 2^c,
             // it will be 2^c - 1 buckets (no bucket for zeroes)
 
-            // Create space for the buckets
-            let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+            // Filling the buckets for a single window is itself independent
+            // work per `(base, scalar)` pair, so it can be split across
+            // threads: every chunk gets its own private bucket array, which
+            // are then reduced elementwise with `Bucket::merge` before the
+            // summation-by-parts step below.
+            #[cfg(feature = "multiexp-intra-window-parallelism")]
+            let buckets: Vec<Bucket<G>> = {
+                let num_bases = exponents.len();
+
+                let chunk_results = pool.scope(num_bases, |scope, chunk_size| {
+                    let chunk_size = std::cmp::max(chunk_size, 1);
+                    let num_chunks = (num_bases + chunk_size - 1) / chunk_size;
+
+                    let mut results: Vec<Option<Result<(G::Projective, Vec<Bucket<G>>), SynthesisError>>> =
+                        (0..num_chunks).map(|_| None).collect();
+
+                    for (chunk_idx, slot) in results.chunks_mut(1).enumerate() {
+                        let start = chunk_idx * chunk_size;
+                        let len = std::cmp::min(chunk_size, num_bases - start);
+
+                        let bases = bases.clone();
+                        let exponents = &exponents;
+                        let density_map = &density_map;
+
+                        scope.spawn(move |_| {
+                            slot[0] = Some(process_window_chunk(
+                                bases,
+                                start,
+                                len,
+                                density_map,
+                                exponents,
+                                skip,
+                                c,
+                                // Every chunk must honor `handle_trivial`
+                                // the same way, not just chunk 0: an
+                                // exp == 1 base can land in any chunk, and
+                                // gating this on `chunk_idx == 0` silently
+                                // dropped those bases in other chunks.
+                                handle_trivial,
+                            ));
+                        });
+                    }
+
+                    results
+                });
+
+                let mut buckets: Vec<Bucket<G>> = (0..(1 << c) - 1).map(|_| Bucket::None).collect();
 
+                for chunk_result in chunk_results {
+                    let (chunk_acc, chunk_buckets) = chunk_result
+                        .expect("every chunk is assigned a task")?;
+                    acc.add_assign(&chunk_acc);
+
+                    for (bucket, chunk_bucket) in buckets.iter_mut().zip(chunk_buckets.into_iter()) {
+                        *bucket = std::mem::replace(bucket, Bucket::None).merge(chunk_bucket);
+                    }
+                }
+
+                buckets
+            };
+
+            // Create space for the buckets. Each bucket starts out empty
+            // (`Bucket::None`) and only promotes to an affine or projective
+            // point once it actually receives a base.
+            #[cfg(not(feature = "multiexp-intra-window-parallelism"))]
+            let mut buckets: Vec<Bucket<G>> = (0..(1 << c) - 1).map(|_| Bucket::None).collect();
+
+            #[cfg(not(feature = "multiexp-intra-window-parallelism"))]
             let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+            #[cfg(not(feature = "multiexp-intra-window-parallelism"))]
             let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
 
+            #[cfg(all(feature = "multiexp-batch-affine", not(feature = "multiexp-intra-window-parallelism")))]
+            {
+                // Instead of adding each base into its bucket as soon as we
+                // see it, collect the `(bucket, base)` pairs for this window
+                // and apply them in batches, so the additions can share a
+                // single field inversion (see `batch_add_assign`).
+                let mut pending = Vec::new();
+
+                for (_i, (&exp, density)) in exponents.iter().zip(density_map.as_ref().iter()).enumerate() {
+                    #[cfg(feature = "prefetch")]
+                    {
+                        if let Some(next) = exponents.get(_i + 1) {
+                            prefetch(next as *const _);
+                        }
+                    }
+
+                    if density {
+                        if exp == zero {
+                            bases.skip(1)?;
+                        } else if exp == one {
+                            if handle_trivial {
+                                bases.add_assign_mixed(&mut acc)?;
+                            } else {
+                                bases.skip(1)?;
+                            }
+                        } else {
+                            let exp = get_at(skip / c, c, &exp);
+
+                            if exp != 0 {
+                                pending.push(((exp - 1) as usize, bases.get()?));
+                            } else {
+                                bases.skip(1)?;
+                            }
+                        }
+                    }
+                }
+
+                while !pending.is_empty() {
+                    pending = batch_add_assign(&mut buckets, pending);
+                }
+            }
+
             // Sort the bases into buckets
-            for (&exp, density) in exponents.iter().zip(density_map.as_ref().iter()) {
+            #[cfg(all(not(feature = "multiexp-batch-affine"), not(feature = "multiexp-intra-window-parallelism")))]
+            for (_i, (&exp, density)) in exponents.iter().zip(density_map.as_ref().iter()).enumerate() {
+                #[cfg(feature = "prefetch")]
+                {
+                    if let Some(next) = exponents.get(_i + 1) {
+                        prefetch(next as *const _);
+                    }
+                }
+
                 // Go over density and exponents
                 if density {
                     if exp == zero {
@@ -223,17 +669,15 @@ fn multiexp_inner<Q, D, G, S>(
                             bases.skip(1)?;
                         }
                     } else {
-                        // Place multiplication into the bucket: Separate s * P as 
+                        // Place multiplication into the bucket: Separate s * P as
                         // (s/2^c) * P + (s mod 2^c) P
                         // First multiplication is c bits less, so one can do it,
                         // sum results from different buckets and double it c times,
                         // then add with (s mod 2^c) P parts
-                        let mut exp = exp;
-                        exp.shr(skip);
-                        let exp = exp.as_ref()[0] % (1 << c);
+                        let exp = get_at(skip / c, c, &exp);
 
                         if exp != 0 {
-                            bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
+                            bases.add_assign_to_bucket(&mut buckets[(exp - 1) as usize])?;
                         } else {
                             bases.skip(1)?;
                         }
@@ -246,8 +690,8 @@ fn multiexp_inner<Q, D, G, S>(
             //                    (a) + b +
             //                    ((a) + b) + c
             let mut running_sum = G::Projective::zero();
-            for exp in buckets.into_iter().rev() {
-                running_sum.add_assign(&exp);
+            for bucket in buckets.into_iter().rev() {
+                bucket.add_to(&mut running_sum);
                 acc.add_assign(&running_sum);
             }
 
@@ -261,20 +705,48 @@ fn multiexp_inner<Q, D, G, S>(
         // There isn't another region.
         Box::new(this)
     } else {
-        // There's another region more significant. Calculate and join it with
-        // this region recursively.
-        Box::new(
-            this.join(multiexp_inner(pool, bases, density_map, exponents, skip, c, false))
-                .map(move |(this, mut higher)| {
-                    for _ in 0..c {
-                        higher.double();
-                    }
+        #[cfg(feature = "multiexp-intra-window-parallelism")]
+        {
+            // Each window here already spreads its own work across every
+            // thread in `pool` via `process_window_chunk`/`pool.scope`
+            // above. Fanning out across windows too (as `.join` does below)
+            // would nest that chunking inside the window-level `pool.compute`
+            // tasks that the non-chunked path also uses to run windows
+            // concurrently, oversubscribing `pool` with a second, redundant
+            // layer of parallelism. So with this feature on, windows are
+            // chained with `.and_then` instead: the next window only starts
+            // once this one's chunks have finished.
+            let pool = pool.clone();
+            Box::new(this.and_then(move |this| {
+                multiexp_inner(&pool, bases, density_map, exponents, skip, c, false)
+                    .map(move |mut higher| {
+                        for _ in 0..c {
+                            higher.double();
+                        }
 
-                    higher.add_assign(&this);
+                        higher.add_assign(&this);
 
-                    higher
-                })
-        )
+                        higher
+                    })
+            }))
+        }
+        #[cfg(not(feature = "multiexp-intra-window-parallelism"))]
+        {
+            // There's another region more significant. Calculate and join it
+            // with this region recursively.
+            Box::new(
+                this.join(multiexp_inner(pool, bases, density_map, exponents, skip, c, false))
+                    .map(move |(this, mut higher)| {
+                        for _ in 0..c {
+                            higher.double();
+                        }
+
+                        higher.add_assign(&this);
+
+                        higher
+                    })
+            )
+        }
     }
 }
 
@@ -307,6 +779,79 @@ pub fn multiexp<Q, D, G, S>(
     multiexp_inner(pool, bases, density_map, exponents, 0, c, true)
 }
 
+/// Abstracts `(bases, density, exponents) -> Projective` so an accelerated
+/// implementation (e.g. an OpenCL/CUDA kernel) can stand in for the CPU
+/// Pippenger implementation above. `select_backend` only ever returns
+/// `CpuBackend`, so another implementation is not picked up by
+/// `multiexp_with_backend`.
+pub trait MultiexpBackend<G: CurveAffine>: Send + Sync {
+    fn multiexp(
+        &self,
+        pool: &Worker,
+        bases: Arc<Vec<G>>,
+        density_map: Option<DensityTracker>,
+        exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+    ) -> Box<Future<Item=<G as CurveAffine>::Projective, Error=SynthesisError>>;
+}
+
+/// The default backend: the CPU Pippenger implementation in this module.
+/// `density_map` of `None` is treated as `FullDensity`.
+pub struct CpuBackend;
+
+impl<G: CurveAffine> MultiexpBackend<G> for CpuBackend {
+    fn multiexp(
+        &self,
+        pool: &Worker,
+        bases: Arc<Vec<G>>,
+        density_map: Option<DensityTracker>,
+        exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+    ) -> Box<Future<Item=<G as CurveAffine>::Projective, Error=SynthesisError>> {
+        match density_map {
+            // `multiexp` requires its density map to implement `AsRef<Q>`
+            // for the `Q` it implements `QueryDensity` for; `DensityTracker`
+            // has no such reflexive impl (only `FullDensity` does), so it
+            // has to be passed through an `Arc` to pick up `AsRef` from the
+            // standard library.
+            Some(density_map) => multiexp(pool, (bases, 0), Arc::new(density_map), exponents),
+            None => multiexp(pool, (bases, 0), FullDensity, exponents),
+        }
+    }
+}
+
+/// Name of the only backend compiled into this crate; any other non-empty
+/// value of `BELLMAN_MULTIEXP_BACKEND` falls back to it with a warning.
+const CPU_BACKEND_NAME: &str = "cpu";
+
+/// Picks the backend `multiexp_with_backend` routes through. It reads
+/// `BELLMAN_MULTIEXP_BACKEND`, but every value resolves to `CpuBackend`:
+/// `"cpu"`, an empty value or an unset variable select it silently, and any
+/// other value prints a warning first. There is no way to register another
+/// backend with this function.
+pub fn select_backend<G: CurveAffine>() -> Box<MultiexpBackend<G>> {
+    match std::env::var("BELLMAN_MULTIEXP_BACKEND") {
+        Ok(ref name) if name == CPU_BACKEND_NAME || name.is_empty() => {},
+        Ok(name) => eprintln!(
+            "bellman: unknown multiexp backend {:?} requested via BELLMAN_MULTIEXP_BACKEND, falling back to {:?}",
+            name, CPU_BACKEND_NAME
+        ),
+        Err(_) => {},
+    }
+
+    Box::new(CpuBackend)
+}
+
+/// Like `multiexp`, but routed through whichever backend `select_backend`
+/// picks. That is always `CpuBackend`, so the result is computed by the CPU
+/// Pippenger implementation.
+pub fn multiexp_with_backend<G: CurveAffine>(
+    pool: &Worker,
+    bases: Arc<Vec<G>>,
+    density_map: Option<DensityTracker>,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> Box<Future<Item=<G as CurveAffine>::Projective, Error=SynthesisError>> {
+    select_backend::<G>().multiexp(pool, bases, density_map, exponents)
+}
+
 #[test]
 fn test_with_bls12() {
     fn naive_multiexp<G: CurveAffine>(
@@ -348,6 +893,162 @@ fn test_with_bls12() {
     assert_eq!(naive, fast);
 }
 
+#[cfg(feature = "multiexp-intra-window-parallelism")]
+#[test]
+fn test_with_density_map() {
+    use rand::{self, Rand};
+    use pairing::bls12_381::Bls12;
+
+    const SAMPLES: usize = 1 << 10;
+
+    let rng = &mut rand::thread_rng();
+
+    let mut density = DensityTracker::new();
+    let mut exponents = Vec::with_capacity(SAMPLES);
+    let mut bases = Vec::new();
+    let mut dense_exponents = Vec::new();
+
+    for i in 0..SAMPLES {
+        density.add_element();
+
+        // Skip roughly a third of the exponents, so bases don't line up
+        // 1:1 with exponents: this is the case `process_window_chunk`'s
+        // per-chunk base skip has to get right.
+        if i % 3 == 0 {
+            exponents.push(<Bls12 as ScalarEngine>::Fr::zero().into_repr());
+        } else {
+            density.inc(i);
+            let exp = <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr();
+            exponents.push(exp);
+            bases.push(<Bls12 as Engine>::G1::rand(rng).into_affine());
+            dense_exponents.push(exp);
+        }
+    }
+
+    let mut naive = <Bls12 as Engine>::G1::zero();
+    for (base, exp) in bases.iter().zip(dense_exponents.iter()) {
+        naive.add_assign(&base.mul(*exp));
+    }
+
+    let pool = Worker::new();
+
+    let fast = multiexp(
+        &pool,
+        (Arc::new(bases), 0),
+        Arc::new(density),
+        Arc::new(exponents)
+    ).wait().unwrap();
+
+    assert_eq!(naive, fast);
+}
+
+/// Smoke test that just exercises `multiexp` with the `prefetch` feature
+/// on, so the cfg'd prefetch calls in the bucket-filling loops get
+/// type-checked and run rather than only ever compiled out.
+#[cfg(feature = "prefetch")]
+#[test]
+fn test_with_prefetch() {
+    use rand::{self, Rand};
+    use pairing::bls12_381::Bls12;
+
+    const SAMPLES: usize = 1 << 10;
+
+    let rng = &mut rand::thread_rng();
+    let v = Arc::new((0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let mut naive = <Bls12 as Engine>::G1::zero();
+    for (base, exp) in g.iter().zip(v.iter()) {
+        naive.add_assign(&base.mul(*exp));
+    }
+
+    let pool = Worker::new();
+    let fast = multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap();
+
+    assert_eq!(naive, fast);
+}
+
+#[test]
+fn test_multiexp_with_backend() {
+    use rand::{self, Rand};
+    use pairing::bls12_381::Bls12;
+
+    const SAMPLES: usize = 1 << 10;
+
+    let rng = &mut rand::thread_rng();
+    let v = Arc::new((0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+
+    // `None` density map: should behave exactly like `FullDensity`.
+    let direct = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+    let via_backend = multiexp_with_backend(&pool, g.clone(), None, v.clone()).wait().unwrap();
+    assert_eq!(direct, via_backend);
+
+    // `Some` density map: every element present, so the result should
+    // still match the `None`/`FullDensity` case above.
+    let mut density = DensityTracker::new();
+    for _ in 0..SAMPLES {
+        density.add_element();
+    }
+    for i in 0..SAMPLES {
+        density.inc(i);
+    }
+
+    let via_backend_with_density = multiexp_with_backend(&pool, g, Some(density), v).wait().unwrap();
+    assert_eq!(direct, via_backend_with_density);
+}
+
+#[cfg(feature = "multiexp-batch-affine")]
+#[test]
+fn test_batch_add_assign() {
+    use rand::{self, Rand};
+    use pairing::bls12_381::G1Affine;
+
+    type Projective = <G1Affine as CurveAffine>::Projective;
+
+    let rng = &mut rand::thread_rng();
+
+    const BUCKETS: usize = 8;
+    const PER_BUCKET: usize = 5;
+
+    let mut buckets: Vec<Bucket<G1Affine>> = (0..BUCKETS).map(|_| Bucket::None).collect();
+    let mut expected: Vec<Projective> = (0..BUCKETS).map(|_| Projective::zero()).collect();
+    let mut pending = Vec::new();
+
+    // Bucket 0 first receives a base immediately followed by its negation;
+    // the pair must cancel back down to `Bucket::None` rather than going
+    // through the generic affine formula.
+    let base = Projective::rand(rng).into_affine();
+    let mut neg = base;
+    neg.negate();
+    expected[0].add_assign_mixed(&base);
+    expected[0].add_assign_mixed(&neg);
+    pending.push((0, base));
+    pending.push((0, neg));
+
+    // Several bases per bucket forces `batch_add_assign` to defer and
+    // retry the later arrivals across more than one pass.
+    for idx in 0..BUCKETS {
+        for _ in 0..PER_BUCKET {
+            let base = Projective::rand(rng).into_affine();
+            expected[idx].add_assign_mixed(&base);
+            pending.push((idx, base));
+        }
+    }
+
+    while !pending.is_empty() {
+        pending = batch_add_assign(&mut buckets, pending);
+    }
+
+    for (bucket, expected) in buckets.into_iter().zip(expected.into_iter()) {
+        let mut acc = Projective::zero();
+        bucket.add_to(&mut acc);
+        assert_eq!(acc, expected);
+    }
+}
+
 #[test]
 fn test_speed_with_bn256() {
     use rand::{self, Rand};